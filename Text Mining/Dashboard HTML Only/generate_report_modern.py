@@ -1,6 +1,8 @@
 # generate_report.py (Version 2.0)
 import json
 import argparse
+import math
+import re
 from jinja2 import Environment, FileSystemLoader
 import sys
 import os
@@ -10,12 +12,222 @@ from collections import Counter, defaultdict
 TEMPLATE_FILE = "template_modern.html.j2"
 PATTERN_RANKING_COUNT = 150
 DIVERSE_RELATION_COUNT = 20
+DIVERSE_RELATION_METRIC = "diversity"
+# Ranking metrics for the diverse-relations list, mapped to their display labels
+DIVERSE_RELATION_METRICS = {
+    "diversity": "Domain/Range Diversity",
+    "frequency": "Frequency",
+    "weighted_frequency": "Diversity-Weighted Frequency",
+}
+# Example triples kept per relation in the induced domain/range report
+RELATION_EXAMPLE_COUNT = 3
+# Share of a relation's uses that its suggested domain/range classes must cover
+DOMAIN_RANGE_COVERAGE = 0.8
+# Minimum co-occurrence count for association scores, and rows shown per association list
+ASSOCIATION_MIN_COUNT = 2
+ASSOCIATION_DISPLAY_COUNT = 10
+# Default weights for certainty-weighted pattern and relation counts. Levels not
+# listed here take the lowest listed weight; extractions without any certainty
+# level (e.g. the basic schema) count with weight 1
+CERTAINTY_WEIGHTS = {"certain": 1.0, "probable": 0.6, "uncertain": 0.3}
+# Percentiles of the entity-type utterance counts that separate low/medium and medium/high
+FREQUENCY_BAND_PERCENTILES = (50, 90)
 
 
-def generate_html_report(json_path: str, output_path: str) -> None:
+def percentile_value(sorted_values: list, percentile: float) -> int:
+    """Nearest-rank percentile of an ascending list (0 for an empty list)."""
+    if not sorted_values:
+        return 0
+    rank = max(1, math.ceil(percentile / 100 * len(sorted_values)))
+    return sorted_values[min(rank, len(sorted_values)) - 1]
+
+
+def normalize_extraction(extraction: dict) -> dict:
+    """
+    Maps an ontology-schema extraction (subject/object with ontology_mapping,
+    provenance.evidence_text) onto the basic-schema keys used throughout the
+    report, keeping its original fields. Basic-schema extractions pass through.
+    """
+    if "subject_entity" in extraction or "subject" not in extraction:
+        return extraction
+    subj = extraction.get("subject") or {}
+    rel = extraction.get("relation") or {}
+    obj = extraction.get("object") or {}
+    normalized = dict(extraction)
+    normalized["subject_entity"] = {
+        "name": subj.get("canonical_name"),
+        "entity_type": (subj.get("ontology_mapping") or {}).get("class"),
+    }
+    normalized["relation"] = {
+        **rel,
+        "semantic_form": (rel.get("ontology_mapping") or {}).get("property"),
+    }
+    normalized["object_entity"] = {
+        "name": obj.get("canonical_name"),
+        "entity_type": (obj.get("ontology_mapping") or {}).get("class"),
+    }
+    normalized["evidence_text"] = (extraction.get("provenance") or {}).get("evidence_text", "")
+    return normalized
+
+
+def extraction_certainty(extraction: dict):
+    """Certainty level of an extraction's epistemic stance, or None if absent."""
+    certainty = (extraction.get("epistemic_stance") or {}).get("certainty_level")
+    if isinstance(certainty, dict):
+        certainty = certainty.get("class")
+    return certainty or None
+
+
+def parse_certainty_weights(pairs: list) -> dict:
+    """Merges LEVEL=WEIGHT pairs onto CERTAINTY_WEIGHTS."""
+    weights = dict(CERTAINTY_WEIGHTS)
+    for pair in pairs:
+        level, sep, weight = pair.partition("=")
+        if not sep or not level:
+            raise ValueError(f"Invalid certainty weight '{pair}', expected LEVEL=WEIGHT.")
+        try:
+            value = float(weight)
+        except ValueError:
+            raise ValueError(f"Invalid certainty weight '{pair}', WEIGHT must be a number.") from None
+        if not math.isfinite(value) or value < 0:
+            raise ValueError(f"Invalid certainty weight '{pair}', WEIGHT must be finite and non-negative.")
+        weights[level] = value
+    return weights
+
+
+def certainty_weight(extraction: dict, certainty_weights: dict) -> float:
+    """Count weight of an extraction under `certainty_weights` (1 when unweighted)."""
+    if not certainty_weights:
+        return 1
+    certainty = extraction_certainty(extraction)
+    if certainty is None:
+        return 1
+    return certainty_weights.get(certainty, min(certainty_weights.values()))
+
+
+def suggest_classes(class_counts: Counter, coverage: float = DOMAIN_RANGE_COVERAGE) -> list:
+    """Most frequent classes that together cover `coverage` of a relation's uses."""
+    total = sum(class_counts.values())
+    suggested, covered = [], 0
+    for cls, count in class_counts.most_common():
+        suggested.append(cls)
+        covered += count
+        if covered >= coverage * total:
+            break
+    return suggested
+
+
+def owl_local_name(name: str) -> str:
+    """Turtle-safe local name for a class or property label."""
+    return re.sub(r"\W+", "_", str(name).strip("<>")).strip("_") or "Unnamed"
+
+
+def domain_range_declaration(rel: str, domain: list, range_: list) -> str:
+    """Suggested rdfs:domain/rdfs:range declaration in Turtle (unions for several classes)."""
+    def class_expression(classes):
+        names = [f":{owl_local_name(c)}" for c in classes]
+        return names[0] if len(names) == 1 else f"[ owl:unionOf ( {' '.join(names)} ) ]"
+    return f":{owl_local_name(rel)} rdfs:domain {class_expression(domain)} ; rdfs:range {class_expression(range_)} ."
+
+
+def association_scores(pair_counts: Counter, min_count: float = ASSOCIATION_MIN_COUNT) -> list:
+    """
+    PMI (log2 of observed over expected) and log-likelihood G² (2x2 contingency
+    table) for each (left, right) pair seen at least `min_count` times, with the
+    strongest positive associations first.
+    """
+    total = sum(pair_counts.values())
+    left_totals, right_totals = Counter(), Counter()
+    for (left, right), count in pair_counts.items():
+        left_totals[left] += count
+        right_totals[right] += count
+
+    scores = []
+    for (left, right), observed in pair_counts.items():
+        if observed < min_count:
+            continue
+        row, col = left_totals[left], right_totals[right]
+        expected = row * col / total
+        cells = [
+            (observed, expected),
+            (row - observed, row * (total - col) / total),
+            (col - observed, (total - row) * col / total),
+            (total - row - col + observed, (total - row) * (total - col) / total),
+        ]
+        log_likelihood = 2 * sum(o * math.log(o / e) for o, e in cells if o > 0 and e > 0)
+        scores.append({
+            "left": left,
+            "right": right,
+            "count": round(observed, 2),
+            "expected": round(expected, 3),
+            "pmi": round(math.log2(observed / expected), 3) + 0.0,
+            "log_likelihood": round(log_likelihood, 3),
+        })
+    scores.sort(key=lambda x: (x["pmi"] > 0, x["log_likelihood"]), reverse=True)
+    return scores
+
+
+def distribution_shape_metrics(counts: list) -> dict:
+    """
+    Shape of a frequency distribution: Shannon entropy (bits, and normalized
+    by its maximum), Gini coefficient, share of one-off items, and a Zipf fit
+    (least-squares slope of log frequency against log rank, with its R²).
+    """
+    metrics = {"entropy": 0.0, "normalized_entropy": 0.0, "gini": 0.0,
+               "hapax_ratio": 0.0, "zipf_exponent": 0.0, "zipf_r_squared": 0.0}
+    total = sum(counts)
+    n = len(counts)
+    if not n or not total:
+        return metrics
+
+    entropy = -sum((c / total) * math.log2(c / total) for c in counts if c) + 0.0
+    metrics["entropy"] = round(entropy, 3)
+    metrics["normalized_entropy"] = round(entropy / math.log2(n), 3) if n > 1 else 0.0
+
+    ascending = sorted(counts)
+    weighted_sum = sum((i + 1) * c for i, c in enumerate(ascending))
+    metrics["gini"] = round((2 * weighted_sum) / (n * total) - (n + 1) / n, 3)
+    metrics["hapax_ratio"] = round(sum(1 for c in counts if c == 1) / n, 3)
+
+    if n > 1:
+        xs = [math.log(rank) for rank in range(1, n + 1)]
+        ys = [math.log(c) for c in sorted(counts, reverse=True)]
+        mean_x, mean_y = sum(xs) / n, sum(ys) / n
+        sxx = sum((x - mean_x) ** 2 for x in xs)
+        sxy = sum((x - mean_x) * (y - mean_y) for x, y in zip(xs, ys))
+        syy = sum((y - mean_y) ** 2 for y in ys)
+        slope = sxy / sxx
+        metrics["zipf_exponent"] = round(-slope, 3) + 0.0
+        metrics["zipf_r_squared"] = round((sxy * sxy) / (sxx * syy), 3) if syy else 1.0
+    return metrics
+
+
+def utterance_range_label(low: int, high: int) -> str:
+    """Human-readable label for an inclusive range of utterance counts."""
+    if low > high:
+        return "None"
+    if low == high:
+        return f"{low} Utterance" if low == 1 else f"{low} Utterances"
+    return f"{low}-{high} Utterances"
+
+
+def generate_html_report(
+    json_path: str,
+    output_path: str,
+    diverse_relation_count: int = DIVERSE_RELATION_COUNT,
+    diverse_relation_metric: str = DIVERSE_RELATION_METRIC,
+    frequency_band_percentiles: tuple = FREQUENCY_BAND_PERCENTILES,
+    certainty_weights: dict = None,
+) -> None:
     """
     Parses JSON, performs frequency analysis on entity types and structural
     patterns, and generates a rich, interactive HTML report.
+
+    `diverse_relation_count` limits the diverse-relations list (0 keeps all),
+    and `diverse_relation_metric` selects its ranking from DIVERSE_RELATION_METRICS.
+    Entity types are banded by `frequency_band_percentiles` of their utterance counts.
+    If `certainty_weights` is given, structural-pattern and relation-frequency
+    counts are weighted by each extraction's certainty level.
     """
     print("[*] Starting report generation...")
     print(f"    - Input JSON: {json_path}")
@@ -48,6 +260,9 @@ def generate_html_report(json_path: str, output_path: str) -> None:
         all_object_types = set()
         relation_domain = defaultdict(set)
         relation_range = defaultdict(set)
+        relation_domain_counts = defaultdict(Counter)
+        relation_range_counts = defaultdict(Counter)
+        relation_examples = defaultdict(list)
         subj_rel_to_obj = defaultdict(set)
         obj_rel_to_subj = defaultdict(set)
         # Frequency of each relation across all extractions
@@ -56,12 +271,15 @@ def generate_html_report(json_path: str, output_path: str) -> None:
         # ▼▼▼ NEW GLOBAL STATS ▼▼▼
         total_extractions = 0
         unique_entity_names = set()
+        entity_name_counts = Counter()
+        entity_pair_counts = Counter()
         unique_relations = set()
         # ▲▲▲ END NEW STATS ▲▲▲
 
         for i, turn in enumerate(speaker_turns_data):
             turn_id = (turn.get('speaker_name', ''), turn.get('utterance_order', i))
             
+            turn['extractions'] = [normalize_extraction(e) for e in turn.get("extractions", [])]
             # Add a turn-level extraction count for convenience in the template
             turn['extraction_count'] = len(turn.get("extractions", []))
             total_extractions += turn['extraction_count']
@@ -76,10 +294,11 @@ def generate_html_report(json_path: str, output_path: str) -> None:
                 rel_form = rel.get("semantic_form")
                 obj_name = obj.get("name")
                 obj_type = obj.get("entity_type")
+                weight = certainty_weight(extraction, certainty_weights)
                 
                 if rel_form:
                     unique_relations.add(rel_form)
-                    relation_frequency_map.update([rel_form])
+                    relation_frequency_map.update({rel_form: weight})
 
                 if subj_type:
                     entity_type_counts.update([subj_type])
@@ -88,6 +307,7 @@ def generate_html_report(json_path: str, output_path: str) -> None:
                     if subj_name:
                         multi_type_entities[subj_name].add(subj_type)
                         unique_entity_names.add(subj_name)
+                        entity_name_counts.update([subj_name])
 
                 if obj_type:
                     entity_type_counts.update([obj_type])
@@ -96,16 +316,29 @@ def generate_html_report(json_path: str, output_path: str) -> None:
                     if obj_name:
                         multi_type_entities[obj_name].add(obj_type)
                         unique_entity_names.add(obj_name)
+                        entity_name_counts.update([obj_name])
 
 
+                if subj_name and obj_name:
+                    entity_pair_counts.update([(subj_name, obj_name)])
+
                 if subj_type and rel_form and obj_type:
                     pattern = (subj_type, rel_form, obj_type)
-                    structural_pattern_counts.update([pattern])
+                    structural_pattern_counts.update({pattern: weight})
                     relation_domain[rel_form].add(subj_type)
                     relation_range[rel_form].add(obj_type)
+                    relation_domain_counts[rel_form].update([subj_type])
+                    relation_range_counts[rel_form].update([obj_type])
+                    if len(relation_examples[rel_form]) < RELATION_EXAMPLE_COUNT:
+                        relation_examples[rel_form].append({"subject": subj_name, "object": obj_name})
                     subj_rel_to_obj[(subj_type, rel_form)].add(obj_type)
                     obj_rel_to_subj[(obj_type, rel_form)].add(subj_type)
 
+        if certainty_weights:
+            structural_pattern_counts = Counter({k: round(v, 2) for k, v in structural_pattern_counts.items()})
+            relation_frequency_map = Counter({k: round(v, 2) for k, v in relation_frequency_map.items()})
+            print(f"[*] Weighted structural patterns and relation frequencies by certainty: {certainty_weights}")
+
         # 4. Prepare Data for Template
         all_entity_types = []
         for entity_name, total_count in entity_type_counts.items():
@@ -119,17 +352,28 @@ def generate_html_report(json_path: str, output_path: str) -> None:
         print(f"[*] Counted {len(all_entity_types)} unique entity types.")
 
         # ▼▼▼ NEW: Categorize entity types by utterance frequency ▼▼▼
+        # Band cut-points are quantiles of the observed utterance counts, so the
+        # bands stay meaningful as the corpus grows.
+        sorted_utterance_counts = sorted(etype['utterance_count'] for etype in all_entity_types)
+        medium_cut = percentile_value(sorted_utterance_counts, frequency_band_percentiles[0])
+        high_cut = max(percentile_value(sorted_utterance_counts, frequency_band_percentiles[1]), medium_cut)
+        frequency_band_cuts = {"medium": medium_cut, "high": high_cut}
+        frequency_band_labels = {
+            "high": f"More than {high_cut} Utterances",
+            "medium": utterance_range_label(medium_cut + 1, high_cut),
+            "low": utterance_range_label(1, medium_cut),
+        }
         entity_types_high_freq = []
         entity_types_medium_freq = []
         entity_types_low_freq = []
         for etype in all_entity_types:
-            if etype['utterance_count'] > 3:
+            if etype['utterance_count'] > high_cut:
                 entity_types_high_freq.append(etype)
-            elif etype['utterance_count'] >= 2:
+            elif etype['utterance_count'] > medium_cut:
                 entity_types_medium_freq.append(etype)
             else:
                 entity_types_low_freq.append(etype)
-        print(f"[*] Categorized entity types: {len(entity_types_high_freq)} High, {len(entity_types_medium_freq)} Medium, {len(entity_types_low_freq)} Low frequency.")
+        print(f"[*] Categorized entity types (cut-points {medium_cut}/{high_cut} utterances): {len(entity_types_high_freq)} High, {len(entity_types_medium_freq)} Medium, {len(entity_types_low_freq)} Low frequency.")
         # ▲▲▲ END NEW CATEGORIZATION ▲▲▲
         
         entity_utterance_counts_map = {item['name']: item['utterance_count'] for item in all_entity_types}
@@ -165,15 +409,52 @@ def generate_html_report(json_path: str, output_path: str) -> None:
         for rel in all_rels:
             domain_size = len(relation_domain.get(rel, []))
             range_size = len(relation_range.get(rel, []))
+            total_diversity = domain_size + range_size
+            frequency = relation_frequency_map.get(rel, 0)
+            if diverse_relation_metric == "frequency":
+                score = frequency
+            elif diverse_relation_metric == "weighted_frequency":
+                score = total_diversity * frequency
+            else:
+                score = total_diversity
+            # Induced schema: observed class distributions and a suggested declaration
+            suggested_domain = suggest_classes(relation_domain_counts[rel])
+            suggested_range = suggest_classes(relation_range_counts[rel])
             relation_diversity.append({
                 'rel': rel,
                 'domain_size': domain_size,
                 'range_size': range_size,
-                'total_diversity': domain_size + range_size
+                'total_diversity': total_diversity,
+                'frequency': frequency,
+                'score': score,
+                'domain_distribution': relation_domain_counts[rel].most_common(),
+                'range_distribution': relation_range_counts[rel].most_common(),
+                'examples': relation_examples[rel],
+                'suggested_domain': suggested_domain,
+                'suggested_range': suggested_range,
+                'suggested_declaration': domain_range_declaration(rel, suggested_domain, suggested_range)
             })
         
-        relation_diversity.sort(key=lambda x: (x['total_diversity'], x['domain_size'], x['range_size']), reverse=True)
-        top_diverse_relations = relation_diversity[:DIVERSE_RELATION_COUNT]
+        relation_diversity.sort(key=lambda x: (x['score'], x['total_diversity'], x['domain_size'], x['range_size']), reverse=True)
+        top_diverse_relations = relation_diversity[:diverse_relation_count] if diverse_relation_count > 0 else relation_diversity
+        relation_schema = sorted(relation_diversity, key=lambda x: (x['frequency'], x['rel']), reverse=True)
+
+        # Association scores surface unexpectedly strong combinations, not just frequent ones
+        subject_type_relation_counts = Counter()
+        relation_object_type_counts = Counter()
+        for (subj, rel, obj), count in structural_pattern_counts.items():
+            subject_type_relation_counts[(subj, rel)] += count
+            relation_object_type_counts[(rel, obj)] += count
+        associations = {
+            "subject_type_relation": association_scores(subject_type_relation_counts),
+            "relation_object_type": association_scores(relation_object_type_counts),
+            "entity_pair": association_scores(entity_pair_counts),
+        }
+        top_associations = {
+            kind: [item for item in scores if item["pmi"] > 0][:ASSOCIATION_DISPLAY_COUNT]
+            for kind, scores in associations.items()
+        }
+        print(f"[*] Scored associations: {', '.join(f'{len(v)} {k}' for k, v in associations.items())}.")
 
         # Build sorted lists by relation frequency for cardinality sections
         one_to_one_relations_sorted = sorted(
@@ -200,6 +481,9 @@ def generate_html_report(json_path: str, output_path: str) -> None:
             "unique_entity_names": len(unique_entity_names),
             "unique_relations": len(unique_relations)
         }
+        # Shape of the entity-name distribution, to spot long tails of one-off entities
+        for metric, value in distribution_shape_metrics(list(entity_name_counts.values())).items():
+            global_stats[f"entity_name_{metric}"] = value
         print(f"[*] Global stats computed: {global_stats}")
         # ▲▲▲ END NEW STATS DICTIONARY ▲▲▲
         
@@ -214,6 +498,8 @@ def generate_html_report(json_path: str, output_path: str) -> None:
             entity_types_high_freq=entity_types_high_freq,
             entity_types_medium_freq=entity_types_medium_freq,
             entity_types_low_freq=entity_types_low_freq,
+            frequency_band_cuts=frequency_band_cuts,
+            frequency_band_labels=frequency_band_labels,
             entity_utterance_counts_map=entity_utterance_counts_map,
             entity_total_counts_map=entity_total_counts_map,
             most_frequent_patterns=most_frequent_patterns,
@@ -228,7 +514,11 @@ def generate_html_report(json_path: str, output_path: str) -> None:
             one_to_many_relations_sorted=one_to_many_relations_sorted,
             many_to_one_relations_sorted=many_to_one_relations_sorted,
             relation_frequency_map=dict(relation_frequency_map),
-            top_diverse_relations=top_diverse_relations
+            top_diverse_relations=top_diverse_relations,
+            diverse_relation_metric_label=DIVERSE_RELATION_METRICS[diverse_relation_metric],
+            relation_schema=relation_schema,
+            associations=associations,
+            top_associations=top_associations
         )
         print("[*] HTML content rendered successfully.")
 
@@ -248,10 +538,25 @@ def main():
         description="Generates a beautiful, self-contained HTML report from relation extraction JSON data.",
         formatter_class=argparse.RawTextHelpFormatter
     )
-    parser.add_argument("--input", "-i", required=True, help="Path to the input JSON file.", metavar="PATH")
+    parser.add_argument("--input", "-i", required=True, help="Path to the input JSON file (basic or ontology-population schema).", metavar="PATH")
     parser.add_argument("--output", "-o", required=True, help="Path to save the generated HTML report.", metavar="PATH")
+    parser.add_argument("--diverse-relations", type=int, default=DIVERSE_RELATION_COUNT, help=f"Number of relations in the diverse-relations list, 0 for all (default: {DIVERSE_RELATION_COUNT}).", metavar="N")
+    parser.add_argument("--diverse-relation-metric", choices=list(DIVERSE_RELATION_METRICS), default=DIVERSE_RELATION_METRIC, help=f"Ranking metric for the diverse-relations list (default: {DIVERSE_RELATION_METRIC}).")
+    parser.add_argument("--frequency-percentiles", type=float, nargs=2, default=FREQUENCY_BAND_PERCENTILES, help=f"Percentiles of entity-type utterance counts separating low/medium and medium/high bands (default: {FREQUENCY_BAND_PERCENTILES[0]:g} {FREQUENCY_BAND_PERCENTILES[1]:g}).", metavar=("LOW", "HIGH"))
+    parser.add_argument("--certainty-weights", nargs="*", help="Weight structural-pattern and relation counts by the epistemic_stance certainty level\n(ontology-population schema). Without values the defaults are used; LEVEL=WEIGHT pairs\noverride them, and unlisted levels take the lowest weight\n" + f"(defaults: {' '.join(f'{k}={v:g}' for k, v in CERTAINTY_WEIGHTS.items())}).", metavar="LEVEL=WEIGHT")
     args = parser.parse_args()
-    generate_html_report(args.input, args.output)
+    if args.diverse_relations < 0:
+        parser.error("--diverse-relations must be 0 (all) or a positive number")
+    low_pct, high_pct = args.frequency_percentiles
+    if not 0 <= low_pct <= high_pct <= 100:
+        parser.error("--frequency-percentiles must satisfy 0 <= LOW <= HIGH <= 100")
+    certainty_weights = None
+    if args.certainty_weights is not None:
+        try:
+            certainty_weights = parse_certainty_weights(args.certainty_weights)
+        except ValueError as e:
+            parser.error(str(e))
+    generate_html_report(args.input, args.output, args.diverse_relations, args.diverse_relation_metric, (low_pct, high_pct), certainty_weights)
 
 if __name__ == "__main__":
     main()